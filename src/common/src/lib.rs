@@ -0,0 +1,8 @@
+// See LICENSE file for copyright and license details.
+
+extern crate cgmath;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+pub mod types;
@@ -0,0 +1,43 @@
+// See LICENSE file for copyright and license details.
+
+use cgmath::Vector2;
+use serde::de::{Deserialize, Deserializer};
+
+/// A tile coordinate on the offset grid.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct MapPos {
+    pub v: Vector2<i32>,
+}
+
+// cgmath's `Vector2` only implements `Deserialize` when cgmath is built with
+// its (optional, not enabled here) "serde" feature, so `MapPos` can't just
+// derive it; deserialize the two fields ourselves instead.
+impl<'de> Deserialize<'de> for MapPos {
+    fn deserialize<D>(deserializer: D) -> Result<MapPos, D::Error>
+        where D: Deserializer<'de>
+    {
+        #[derive(Deserialize)]
+        struct Raw { x: i32, y: i32 }
+        let raw = Raw::deserialize(deserializer)?;
+        Ok(MapPos{v: Vector2{x: raw.x, y: raw.y}})
+    }
+}
+
+/// Width and height of a map, in tiles.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+pub struct Size2 {
+    pub w: i32,
+    pub h: i32,
+}
+
+/// Identifies one of the match's players.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Deserialize)]
+pub struct PlayerId {
+    pub id: i32,
+}
+
+/// Identifies one unit instance for the lifetime of the match.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct UnitId {
+    pub id: i32,
+}
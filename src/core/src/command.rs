@@ -0,0 +1,36 @@
+// See LICENSE file for copyright and license details.
+
+use common::types::MapPos;
+
+/// How many move points a `Path` costs to walk.
+pub struct MoveCost {
+    pub n: i32,
+}
+
+/// Whether a unit is moving at its normal move-point cost or double cost
+/// (e.g. to stay ready to react).
+pub enum MoveMode {
+    Fast,
+    Slow,
+}
+
+/// A sequence of adjacent tiles a unit walks along, from its current
+/// position (exclusive) to `destination`.
+pub struct Path {
+    tiles: Vec<MapPos>,
+}
+
+impl Path {
+    pub fn new(tiles: Vec<MapPos>) -> Path {
+        assert!(!tiles.is_empty(), "Path must contain at least one tile");
+        Path { tiles: tiles }
+    }
+
+    pub fn destination(&self) -> &MapPos {
+        self.tiles.last().expect("Path must contain at least one tile")
+    }
+
+    pub fn total_cost(&self) -> MoveCost {
+        MoveCost { n: self.tiles.len() as i32 }
+    }
+}
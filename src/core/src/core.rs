@@ -0,0 +1,62 @@
+// See LICENSE file for copyright and license details.
+
+use common::types::{PlayerId, UnitId, MapPos};
+use db::UnitTypeId;
+use command::{MoveMode, Path};
+
+/// Whether an attack is a unit spending its own (`Active`) attack points or
+/// one of its reserved (`Reactive`) ones.
+pub enum FireMode {
+    Active,
+    Reactive,
+}
+
+/// Everything needed to instantiate a unit via `CreateUnit`/`ShowUnit`.
+/// `passanger_id` is only ever populated at `Full` info level — see
+/// `InternalState::add_unit`.
+#[derive(Clone)]
+pub struct UnitInfo {
+    pub unit_id: UnitId,
+    pub pos: MapPos,
+    pub player_id: PlayerId,
+    pub type_id: UnitTypeId,
+    pub passanger_id: Option<UnitId>,
+}
+
+/// One state transition of a match, as applied by `InternalState::apply_event`.
+pub enum CoreEvent {
+    Move {
+        unit_id: UnitId,
+        path: Path,
+        mode: MoveMode,
+    },
+    EndTurn {
+        old_id: PlayerId,
+        new_id: PlayerId,
+    },
+    CreateUnit {
+        unit_info: UnitInfo,
+    },
+    AttackUnit {
+        attacker_id: Option<UnitId>,
+        defender_id: UnitId,
+        mode: FireMode,
+        killed: i32,
+        suppression: i32,
+        remove_move_points: bool,
+    },
+    ShowUnit {
+        unit_info: UnitInfo,
+    },
+    HideUnit {
+        unit_id: UnitId,
+    },
+    LoadUnit {
+        passanger_id: UnitId,
+        transporter_id: UnitId,
+    },
+    UnloadUnit {
+        transporter_id: UnitId,
+        unit_info: UnitInfo,
+    },
+}
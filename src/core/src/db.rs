@@ -0,0 +1,42 @@
+// See LICENSE file for copyright and license details.
+
+use std::collections::{HashMap, HashSet};
+use internal_state::DamageType;
+
+/// Identifies one entry in `Db`'s unit type table (e.g. "rifleman", "tank").
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Deserialize)]
+pub struct UnitTypeId(pub String);
+
+/// The static, game-design data shared by every instance of a unit type:
+/// base stats plus the damage-resolution and fog-of-war inputs
+/// (`damage`/`damage_type`/`weak_to`/`immune_to`/`hp_per_man` and
+/// `sight_range`) that `InternalState::casualties`/`visible_tiles` read.
+#[derive(Clone)]
+pub struct UnitType {
+    pub move_points: i32,
+    pub attack_points: i32,
+    pub reactive_attack_points: i32,
+    pub count: i32,
+    pub is_transporter: bool,
+    pub damage: i32,
+    pub damage_type: DamageType,
+    pub weak_to: HashSet<DamageType>,
+    pub immune_to: HashSet<DamageType>,
+    pub hp_per_man: i32,
+    pub sight_range: i32,
+}
+
+/// The game's static rules database: unit type definitions keyed by id.
+pub struct Db {
+    unit_types: HashMap<UnitTypeId, UnitType>,
+}
+
+impl Db {
+    pub fn new(unit_types: HashMap<UnitTypeId, UnitType>) -> Db {
+        Db { unit_types: unit_types }
+    }
+
+    pub fn unit_type(&self, type_id: &UnitTypeId) -> &UnitType {
+        &self.unit_types[type_id]
+    }
+}
@@ -1,11 +1,15 @@
 // See LICENSE file for copyright and license details.
 
-use std::collections::{HashMap};
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::Path;
 use cgmath::{Vector2};
+use serde_json;
 use common::types::{PlayerId, UnitId, MapPos, Size2};
 use core::{CoreEvent, FireMode, UnitInfo};
 use unit::{Unit};
-use db::{Db};
+use db::{Db, UnitType, UnitTypeId};
 use map::{Map, Terrain};
 use command::{MoveMode};
 
@@ -14,24 +18,178 @@ pub enum InfoLevel {
     Partial,
 }
 
+/// Kind of damage a unit's attack deals, used to look up weaknesses
+/// and immunities on the defender's `UnitType`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DamageType {
+    Bludgeoning,
+    Piercing,
+    Fire,
+    Radiation,
+}
+
+/// Multiplier applied to effective power based on the defender's
+/// resistances to `damage_type`.
+fn damage_modifier(defender_type: &UnitType, damage_type: DamageType) -> f32 {
+    if defender_type.immune_to.contains(&damage_type) {
+        0.0
+    } else if defender_type.weak_to.contains(&damage_type) {
+        2.0
+    } else {
+        1.0
+    }
+}
+
+/// (xx, xy, yx, yy) transforms that rotate/mirror a single octant's local
+/// (col, row) coordinates into each of the 8 octants around an origin.
+const OCTANT_TRANSFORMS: [(i32, i32, i32, i32); 8] = [
+    (1, 0, 0, 1), (0, 1, 1, 0), (0, -1, 1, 0), (-1, 0, 0, 1),
+    (-1, 0, 0, -1), (0, -1, -1, 0), (0, 1, -1, 0), (1, 0, 0, -1),
+];
+
+const MORALE_MIN: i32 = 0;
+const MORALE_MAX: i32 = 100;
+const SUPPRESSED_MORALE_THRESHOLD: i32 = 70;
+const PINNED_MORALE_THRESHOLD: i32 = 40;
+const BROKEN_MORALE_THRESHOLD: i32 = 15;
+
+/// How badly a unit's morale is hurting its combat effectiveness.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MoraleState {
+    Steady,
+    Suppressed,
+    Pinned,
+    Broken,
+}
+
+fn morale_state_for(morale: i32) -> MoraleState {
+    if morale < BROKEN_MORALE_THRESHOLD {
+        MoraleState::Broken
+    } else if morale < PINNED_MORALE_THRESHOLD {
+        MoraleState::Pinned
+    } else if morale < SUPPRESSED_MORALE_THRESHOLD {
+        MoraleState::Suppressed
+    } else {
+        MoraleState::Steady
+    }
+}
+
+/// The 8 tiles adjacent to `pos` on the offset grid.
+fn neighbors(pos: &MapPos) -> Vec<MapPos> {
+    let offsets = [
+        (-1, -1), (0, -1), (1, -1),
+        (-1, 0), (1, 0),
+        (-1, 1), (0, 1), (1, 1),
+    ];
+    offsets.iter()
+        .map(|&(dx, dy)| MapPos{v: Vector2{x: pos.v.x + dx, y: pos.v.y + dy}})
+        .collect()
+}
+
+fn tile_distance(a: &MapPos, b: &MapPos) -> i32 {
+    cmp::max((a.v.x - b.v.x).abs(), (a.v.y - b.v.y).abs())
+}
+
+/// A terrain tile that differs from the scenario's default `Plain`.
+#[derive(Deserialize)]
+pub struct TerrainOverride {
+    pub pos: MapPos,
+    pub terrain: Terrain,
+}
+
+/// One entry in a scenario's order of battle. A `passanger` is created
+/// at its own `pos`, which must be this unit's tile or an adjacent one,
+/// and immediately loaded into this unit, which moves it onto this
+/// unit's tile.
+#[derive(Deserialize)]
+pub struct UnitPlacement {
+    pub type_id: UnitTypeId,
+    pub player_id: PlayerId,
+    pub pos: MapPos,
+    #[serde(default)]
+    pub passanger: Option<Box<UnitPlacement>>,
+}
+
+/// A map size, terrain overrides and an order of battle, loaded from a
+/// JSON file (see `from_file`) instead of being hard-coded into
+/// `InternalState::new`.
+#[derive(Deserialize)]
+pub struct Scenario {
+    pub map_size: Size2,
+    #[serde(default)]
+    pub terrain: Vec<TerrainOverride>,
+    pub units: Vec<UnitPlacement>,
+}
+
+impl Scenario {
+    pub fn from_file(path: &Path) -> Scenario {
+        let file = File::open(path).expect("Can't open scenario file");
+        serde_json::from_reader(file).expect("Bad scenario json")
+    }
+}
+
 pub struct InternalState {
     units: HashMap<UnitId, Unit>,
     map: Map<Terrain>,
+    tile_units: HashMap<MapPos, Vec<UnitId>>,
 }
 
 impl<'a> InternalState {
-    pub fn new(map_size: &Size2) -> InternalState {
-        let mut map = Map::new(map_size, Terrain::Plain);
-        // TODO: read from scenario.json?
-        *map.tile_mut(&MapPos{v: Vector2{x: 4, y: 3}}) = Terrain::Trees;
-        *map.tile_mut(&MapPos{v: Vector2{x: 4, y: 4}}) = Terrain::Trees;
-        *map.tile_mut(&MapPos{v: Vector2{x: 4, y: 5}}) = Terrain::Trees;
-        *map.tile_mut(&MapPos{v: Vector2{x: 5, y: 5}}) = Terrain::Trees;
-        *map.tile_mut(&MapPos{v: Vector2{x: 6, y: 4}}) = Terrain::Trees;
-        InternalState {
+    /// `next_unit_id` is the match's single running id generator, owned by
+    /// the caller and also used for every `CreateUnit` minted later during
+    /// play (reinforcements, reactivated units, ...). Scenario placement
+    /// must draw from that same cursor rather than a private counter, or
+    /// the ids it hands out here collide with the ones handed out after
+    /// the game starts.
+    pub fn new(db: &Db, scenario: &Scenario, next_unit_id: &mut UnitId) -> InternalState {
+        let mut map = Map::new(&scenario.map_size, Terrain::Plain);
+        for over in &scenario.terrain {
+            *map.tile_mut(&over.pos) = over.terrain.clone();
+        }
+        let mut state = InternalState {
             units: HashMap::new(),
             map: map,
+            tile_units: HashMap::new(),
+        };
+        for placement in &scenario.units {
+            state.place_unit(db, next_unit_id, placement, true);
+        }
+        state
+    }
+
+    /// Applies the `CreateUnit` (and, for a carried unit, `LoadUnit`) events
+    /// needed to instantiate one order-of-battle entry, validating that its
+    /// tile is on the map and, unless it's a passenger (which is loaded
+    /// into its transporter right away), unoccupied.
+    fn place_unit(
+        &mut self,
+        db: &Db,
+        next_unit_id: &mut UnitId,
+        placement: &UnitPlacement,
+        check_occupied: bool,
+    ) -> UnitId {
+        assert!(self.map.is_inboard(&placement.pos), "Scenario places a unit off the map");
+        if check_occupied {
+            assert!(!self.is_tile_occupied(&placement.pos), "Scenario places two units on one tile");
+        }
+        let unit_id = next_unit_id.clone();
+        next_unit_id.id += 1;
+        let unit_info = UnitInfo {
+            unit_id: unit_id.clone(),
+            pos: placement.pos.clone(),
+            player_id: placement.player_id.clone(),
+            type_id: placement.type_id.clone(),
+            passanger_id: None,
+        };
+        self.apply_event(db, &CoreEvent::CreateUnit{unit_info: unit_info});
+        if let Some(ref passanger) = placement.passanger {
+            let passanger_id = self.place_unit(db, next_unit_id, passanger, false);
+            self.apply_event(db, &CoreEvent::LoadUnit{
+                passanger_id: passanger_id,
+                transporter_id: unit_id.clone(),
+            });
         }
+        unit_id
     }
 
     pub fn units(&self) -> &HashMap<UnitId, Unit> {
@@ -46,19 +204,50 @@ impl<'a> InternalState {
         &self.map
     }
 
-    pub fn units_at(&'a self, pos: &MapPos) -> Vec<&'a Unit> {
-        let mut units = Vec::new();
-        for (_, unit) in &self.units {
-            if unit.pos == *pos {
-                units.push(unit);
-            }
+    /// Unit ids occupying `pos`, in no particular order.
+    pub fn tile_content(&'a self, pos: &MapPos) -> &'a [UnitId] {
+        match self.tile_units.get(pos) {
+            Some(ids) => ids,
+            None => &[],
         }
-        units
+    }
+
+    pub fn units_at(&'a self, pos: &MapPos) -> Vec<&'a Unit> {
+        self.tile_content(pos).iter()
+            .map(|id| &self.units[id])
+            .collect()
     }
 
     pub fn is_tile_occupied(&self, pos: &MapPos) -> bool {
-        // TODO: optimize
-        self.units_at(pos).len() > 0
+        !self.tile_content(pos).is_empty()
+    }
+
+    fn index_unit(&mut self, id: &UnitId, pos: &MapPos) {
+        self.tile_units.entry(pos.clone())
+            .or_insert_with(Vec::new)
+            .push(id.clone());
+    }
+
+    fn deindex_unit(&mut self, id: &UnitId, pos: &MapPos) {
+        let is_empty = {
+            let ids = self.tile_units.get_mut(pos)
+                .expect("Unit is not indexed at its tile");
+            let index = ids.iter().position(|i| i == id)
+                .expect("Unit is not indexed at its tile");
+            ids.swap_remove(index);
+            ids.is_empty()
+        };
+        if is_empty {
+            self.tile_units.remove(pos);
+        }
+    }
+
+    fn reindex_unit(&mut self, id: &UnitId, old_pos: &MapPos, new_pos: &MapPos) {
+        if old_pos == new_pos {
+            return;
+        }
+        self.deindex_unit(id, old_pos);
+        self.index_unit(id, new_pos);
     }
 
     /// Converts active ap (attack points) to reactive
@@ -76,22 +265,280 @@ impl<'a> InternalState {
     }
 
     fn refresh_units(&mut self, db: &Db, player_id: &PlayerId) {
-        for (_, unit) in self.units.iter_mut() {
-            if unit.player_id == *player_id {
-                let unit_type = db.unit_type(&unit.type_id);
+        let mut unit_ids: Vec<UnitId> = self.units.iter()
+            .filter(|&(_, unit)| unit.player_id == *player_id)
+            .map(|(id, _)| id.clone())
+            .collect();
+        unit_ids.sort_by_key(|id| id.id);
+        for unit_id in &unit_ids {
+            {
+                let unit_type = db.unit_type(&self.units[unit_id].type_id).clone();
+                let unit = self.units.get_mut(unit_id).expect("Bad unit id");
                 unit.move_points = unit_type.move_points;
                 unit.attack_points = unit_type.attack_points;
                 if let Some(ref mut reactive_attack_points) = unit.reactive_attack_points {
                     *reactive_attack_points = unit_type.reactive_attack_points;
                 }
-                unit.morale += 10;
+                unit.morale = cmp::min(unit.morale + 10, MORALE_MAX);
+            }
+            self.apply_morale_effects(unit_id);
+            if let MoraleState::Broken = self.morale_state(unit_id) {
+                self.retreat(unit_id);
+            }
+        }
+    }
+
+    /// How badly `unit_id`'s morale is hurting its combat effectiveness.
+    pub fn morale_state(&self, unit_id: &UnitId) -> MoraleState {
+        morale_state_for(self.units[unit_id].morale)
+    }
+
+    /// Caps a unit's fighting ability according to its current morale
+    /// state: a suppressed unit loses reactive fire, a pinned unit is also
+    /// capped to a single point of attack and movement, a broken unit can't
+    /// fight back at all (it can still use its move points to flee).
+    fn apply_morale_effects(&mut self, unit_id: &UnitId) {
+        let state = self.morale_state(unit_id);
+        let unit = self.units.get_mut(unit_id).expect("Bad unit id");
+        match state {
+            MoraleState::Steady => {},
+            MoraleState::Suppressed => {
+                if let Some(ref mut reactive_attack_points) = unit.reactive_attack_points {
+                    *reactive_attack_points = 0;
+                }
+            },
+            MoraleState::Pinned => {
+                if let Some(ref mut reactive_attack_points) = unit.reactive_attack_points {
+                    *reactive_attack_points = 0;
+                }
+                unit.attack_points = cmp::min(unit.attack_points, 1);
+                unit.move_points = cmp::min(unit.move_points, 1);
+            },
+            MoraleState::Broken => {
+                unit.attack_points = 0;
+                if let Some(ref mut reactive_attack_points) = unit.reactive_attack_points {
+                    *reactive_attack_points = 0;
+                }
+            },
+        }
+    }
+
+    /// Spends a broken unit's move points fleeing, one tile at a time,
+    /// toward whichever free neighboring tile is farthest from the
+    /// nearest enemy unit.
+    fn retreat(&mut self, unit_id: &UnitId) {
+        let (mut pos, mut move_points, player_id) = {
+            let unit = &self.units[unit_id];
+            (unit.pos.clone(), unit.move_points, unit.player_id.clone())
+        };
+        let nearest_enemy_pos = self.units.values()
+            .filter(|unit| unit.player_id != player_id)
+            .min_by_key(|unit| (tile_distance(&pos, &unit.pos), unit.id.id))
+            .map(|unit| unit.pos.clone());
+        let nearest_enemy_pos = match nearest_enemy_pos {
+            Some(enemy_pos) => enemy_pos,
+            None => return,
+        };
+        while move_points > 0 {
+            let next = neighbors(&pos).into_iter()
+                .filter(|tile| self.map.is_inboard(tile) && !self.is_tile_occupied(tile))
+                .max_by_key(|tile| tile_distance(tile, &nearest_enemy_pos));
+            let next = match next {
+                Some(tile) => tile,
+                None => break,
+            };
+            if tile_distance(&next, &nearest_enemy_pos) <= tile_distance(&pos, &nearest_enemy_pos) {
+                break;
+            }
+            self.reindex_unit(unit_id, &pos, &next);
+            pos = next;
+            move_points -= 1;
+        }
+        let unit = self.units.get_mut(unit_id).expect("Bad unit id");
+        unit.pos = pos;
+        unit.move_points = move_points;
+    }
+
+    /// Resolves how many men of `defender_id` the `attacker_id` kills this
+    /// attack, based on the attacker's effective power and the defender's
+    /// weaknesses/immunities to the attacker's `damage_type`.
+    fn casualties(&self, db: &Db, attacker_id: &UnitId, defender_id: &UnitId) -> i32 {
+        let attacker = &self.units[attacker_id];
+        let defender = &self.units[defender_id];
+        let attacker_type = db.unit_type(&attacker.type_id);
+        let defender_type = db.unit_type(&defender.type_id);
+        let effective_power = attacker.count as f32 * attacker_type.damage as f32;
+        let modifier = damage_modifier(defender_type, attacker_type.damage_type);
+        let total_damage = effective_power * modifier;
+        let killed = (total_damage / defender_type.hp_per_man as f32) as i32;
+        cmp::min(killed, defender.count)
+    }
+
+    /// A tile blocks line of sight if it's off the map or covered in trees.
+    fn is_opaque(&self, pos: &MapPos) -> bool {
+        if !self.map.is_inboard(pos) {
+            return true;
+        }
+        match *self.map.tile(pos) {
+            Terrain::Trees => true,
+            _ => false,
+        }
+    }
+
+    /// Recursive shadowcasting over one octant, rooted at `origin`.
+    /// `start_slope`/`end_slope` bound the cone of the octant still visible
+    /// at `row`; they narrow whenever a blocking tile splits the cone.
+    fn cast_light(
+        &self,
+        origin: &MapPos,
+        radius: i32,
+        row: i32,
+        mut start_slope: f32,
+        end_slope: f32,
+        xx: i32, xy: i32, yx: i32, yy: i32,
+        visible: &mut HashSet<MapPos>,
+    ) {
+        if start_slope < end_slope {
+            return;
+        }
+        let mut next_start_slope = start_slope;
+        for i in row..(radius + 1) {
+            let dy = -i;
+            let mut blocked = false;
+            for dx in -i..1 {
+                let l_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+                let r_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+                if start_slope < r_slope {
+                    continue;
+                } else if end_slope > l_slope {
+                    break;
+                }
+
+                let sax = dx * xx + dy * xy;
+                let say = dx * yx + dy * yy;
+                let pos = MapPos {
+                    v: Vector2{x: origin.v.x + sax, y: origin.v.y + say},
+                };
+
+                if dx * dx + dy * dy <= radius * radius {
+                    visible.insert(pos.clone());
+                }
+
+                if blocked {
+                    if self.is_opaque(&pos) {
+                        next_start_slope = r_slope;
+                        continue;
+                    }
+                    blocked = false;
+                    start_slope = next_start_slope;
+                } else if self.is_opaque(&pos) && i < radius {
+                    blocked = true;
+                    self.cast_light(
+                        origin, radius, i + 1, start_slope, l_slope,
+                        xx, xy, yx, yy, visible,
+                    );
+                    next_start_slope = r_slope;
+                }
+            }
+            if blocked {
+                break;
+            }
+        }
+    }
+
+    /// Every tile `player_id` can currently see, from shadowcasting out of
+    /// each of their units. A unit always sees its own tile; blocking tiles
+    /// are visible themselves but occlude whatever lies behind them.
+    pub fn visible_tiles(&self, db: &Db, player_id: &PlayerId) -> HashSet<MapPos> {
+        let mut visible = HashSet::new();
+        for (_, unit) in &self.units {
+            if unit.player_id != *player_id {
+                continue;
+            }
+            visible.insert(unit.pos.clone());
+            let sight_range = db.unit_type(&unit.type_id).sight_range;
+            for &(xx, xy, yx, yy) in OCTANT_TRANSFORMS.iter() {
+                self.cast_light(&unit.pos, sight_range, 1, 1.0, 0.0, xx, xy, yx, yy, &mut visible);
+            }
+        }
+        visible
+    }
+
+    /// Enemy units currently visible to `player_id`, derived from `visible_tiles`.
+    pub fn visible_enemy_units(&self, db: &Db, player_id: &PlayerId) -> HashSet<UnitId> {
+        let visible_tiles = self.visible_tiles(db, player_id);
+        let mut visible_units = HashSet::new();
+        for (id, unit) in &self.units {
+            if unit.player_id != *player_id && visible_tiles.contains(&unit.pos) {
+                visible_units.insert(id.clone());
             }
         }
+        visible_units
+    }
+
+    /// `ShowUnit`/`HideUnit` events needed to bring `player_id`'s knowledge
+    /// of enemy units in line with `visible_enemy_units`, given the set it
+    /// was previously shown (e.g. the `visible_enemy_units` computed before
+    /// the `Move`/`AttackUnit`/... event that just ran). It applies none of
+    /// the events itself and nothing in this crate calls it; passed to
+    /// `apply_event`, a `ShowUnit` adds the enemy unit with `Partial` info
+    /// (see `add_unit`) and a `HideUnit` removes it.
+    pub fn visibility_events(
+        &self,
+        db: &Db,
+        player_id: &PlayerId,
+        previously_visible: &HashSet<UnitId>,
+    ) -> Vec<CoreEvent> {
+        let now_visible = self.visible_enemy_units(db, player_id);
+        let mut events: Vec<CoreEvent> = now_visible.difference(previously_visible)
+            .map(|unit_id| {
+                let unit = &self.units[unit_id];
+                CoreEvent::ShowUnit {
+                    unit_info: UnitInfo {
+                        unit_id: unit_id.clone(),
+                        pos: unit.pos.clone(),
+                        player_id: unit.player_id.clone(),
+                        type_id: unit.type_id.clone(),
+                        passanger_id: None,
+                    },
+                }
+            })
+            .collect();
+        events.extend(
+            previously_visible.difference(&now_visible)
+                .map(|unit_id| CoreEvent::HideUnit{unit_id: unit_id.clone()})
+        );
+        events
+    }
+
+    /// When a transporter carrying `passanger_id` is destroyed, emergency-
+    /// unload the passenger onto a free adjacent tile; if there's nowhere
+    /// to evacuate to, the passenger is destroyed along with it.
+    fn evacuate_or_kill_passanger(&mut self, transporter_id: &UnitId, passanger_id: &UnitId) {
+        if !self.units.contains_key(passanger_id) {
+            return;
+        }
+        let transporter_pos = self.units[transporter_id].pos.clone();
+        let escape = neighbors(&transporter_pos).into_iter()
+            .find(|tile| self.map.is_inboard(tile) && !self.is_tile_occupied(tile));
+        match escape {
+            Some(tile) => {
+                self.reindex_unit(passanger_id, &transporter_pos, &tile);
+                let passanger = self.units.get_mut(passanger_id).expect("Bad passanger_id");
+                passanger.pos = tile;
+                passanger.move_points = 0;
+            },
+            None => {
+                self.deindex_unit(passanger_id, &transporter_pos);
+                self.units.remove(passanger_id);
+            },
+        }
     }
 
     fn add_unit(&mut self, db: &Db, unit_info: &UnitInfo, info_level: InfoLevel) {
         assert!(self.units.get(&unit_info.unit_id).is_none());
         let unit_type = db.unit_type(&unit_info.type_id);
+        self.index_unit(&unit_info.unit_id, &unit_info.pos);
         self.units.insert(unit_info.unit_id.clone(), Unit {
             id: unit_info.unit_id.clone(),
             pos: unit_info.pos.clone(),
@@ -105,7 +552,7 @@ impl<'a> InternalState {
                 None
             },
             count: unit_type.count,
-            morale: 100,
+            morale: MORALE_MAX,
             passanger_id: if let InfoLevel::Full = info_level {
                 unit_info.passanger_id.clone()
             } else {
@@ -118,19 +565,32 @@ impl<'a> InternalState {
         match event {
             &CoreEvent::Move{ref unit_id, ref path, ref mode} => {
                 let pos = path.destination().clone();
-                let unit = self.units.get_mut(unit_id)
-                    .expect("Bad move unit id");
-                unit.pos = pos;
-                assert!(unit.move_points > 0);
-                if db.unit_type(&unit.type_id).is_transporter {
-                    // TODO: get passanger and update its pos
-                }
-                if let &MoveMode::Fast = mode {
-                    unit.move_points -= path.total_cost().n;
-                } else {
-                    unit.move_points -= path.total_cost().n * 2;
-                }
-                assert!(unit.move_points >= 0);
+                let old_pos = self.units[unit_id].pos.clone();
+                let is_transporter = db.unit_type(&self.units[unit_id].type_id).is_transporter;
+                let passanger_id = self.units[unit_id].passanger_id.clone();
+                self.reindex_unit(unit_id, &old_pos, &pos);
+                {
+                    let unit = self.units.get_mut(unit_id)
+                        .expect("Bad move unit id");
+                    unit.pos = pos.clone();
+                    assert!(unit.move_points > 0);
+                    if let &MoveMode::Fast = mode {
+                        unit.move_points -= path.total_cost().n;
+                    } else {
+                        unit.move_points -= path.total_cost().n * 2;
+                    }
+                    assert!(unit.move_points >= 0);
+                }
+                if is_transporter {
+                    if let Some(passanger_id) = passanger_id {
+                        if self.units.contains_key(&passanger_id) {
+                            self.reindex_unit(&passanger_id, &old_pos, &pos);
+                            let passanger = self.units.get_mut(&passanger_id)
+                                .expect("Bad passanger_id");
+                            passanger.pos = pos.clone();
+                        }
+                    }
+                }
             },
             &CoreEvent::EndTurn{ref new_id, ref old_id} => {
                 self.refresh_units(db, new_id);
@@ -147,19 +607,28 @@ impl<'a> InternalState {
                 ref suppression,
                 ref remove_move_points,
             } => {
+                let killed = match attacker_id {
+                    &Some(ref attacker_id) => self.casualties(db, attacker_id, defender_id),
+                    &None => *killed,
+                };
                 {
                     let unit = self.units.get_mut(defender_id)
                         .expect("Can`t find defender");
-                    unit.count -= *killed;
-                    unit.morale -= *suppression;
+                    unit.count -= killed;
+                    unit.morale = cmp::max(MORALE_MIN, cmp::min(MORALE_MAX, unit.morale - *suppression));
                     if *remove_move_points {
                         unit.move_points = 0;
                     }
                 }
+                self.apply_morale_effects(defender_id);
                 let count = self.units[defender_id].count.clone();
                 if count <= 0 {
-                    // TODO: kill\unload passangers
                     assert!(self.units.get(defender_id).is_some());
+                    if let Some(passanger_id) = self.units[defender_id].passanger_id.clone() {
+                        self.evacuate_or_kill_passanger(defender_id, &passanger_id);
+                    }
+                    let defender_pos = self.units[defender_id].pos.clone();
+                    self.deindex_unit(defender_id, &defender_pos);
                     self.units.remove(defender_id);
                 }
                 let attacker_id = match attacker_id.clone() {
@@ -188,25 +657,47 @@ impl<'a> InternalState {
             },
             &CoreEvent::HideUnit{ref unit_id} => {
                 assert!(self.units.get(unit_id).is_some());
+                let pos = self.units[unit_id].pos.clone();
+                self.deindex_unit(unit_id, &pos);
                 self.units.remove(unit_id);
             },
             &CoreEvent::LoadUnit{ref passanger_id, ref transporter_id} => {
-                // TODO: hide info abiut passanger from enemy player
-                self.units.get_mut(transporter_id)
-                    .expect("Bad transporter_id")
-                    .passanger_id = Some(passanger_id.clone());
                 let transporter_pos = self.units[transporter_id].pos.clone();
-                let passanger = self.units.get_mut(passanger_id)
-                    .expect("Bad passanger_id");
-                passanger.pos = transporter_pos;
-                passanger.move_points = 0;
+                let has_full_info = self.units[transporter_id].reactive_attack_points.is_some();
+                if has_full_info {
+                    assert!(self.units[transporter_id].passanger_id.is_none(),
+                        "Transporter is already carrying a passenger");
+                }
+                if self.units.contains_key(passanger_id) {
+                    let old_pos = self.units[passanger_id].pos.clone();
+                    assert!(tile_distance(&old_pos, &transporter_pos) <= 1,
+                        "Passenger is not adjacent to transporter");
+                    self.reindex_unit(passanger_id, &old_pos, &transporter_pos);
+                    let passanger = self.units.get_mut(passanger_id)
+                        .expect("Bad passanger_id");
+                    passanger.pos = transporter_pos;
+                    passanger.move_points = 0;
+                }
+                // Partial (enemy) observers never learn what's inside a transporter.
+                if has_full_info {
+                    self.units.get_mut(transporter_id)
+                        .expect("Bad transporter_id")
+                        .passanger_id = Some(passanger_id.clone());
+                }
             },
             &CoreEvent::UnloadUnit{ref transporter_id, ref unit_info} => {
+                let transporter_pos = self.units[transporter_id].pos.clone();
+                assert!(tile_distance(&unit_info.pos, &transporter_pos) == 1,
+                    "Unload destination is not adjacent to transporter");
+                assert!(self.tile_content(&unit_info.pos).iter().all(|id| *id == unit_info.unit_id),
+                    "Unload destination is occupied");
                 self.units.get_mut(transporter_id)
                     .expect("Bad transporter_id")
                     .passanger_id = None;
-                if let Some(unit) = self.units.get_mut(&unit_info.unit_id) {
-                    unit.pos = unit_info.pos.clone();
+                if self.units.get(&unit_info.unit_id).is_some() {
+                    let old_pos = self.units[&unit_info.unit_id].pos.clone();
+                    self.reindex_unit(&unit_info.unit_id, &old_pos, &unit_info.pos);
+                    self.units.get_mut(&unit_info.unit_id).unwrap().pos = unit_info.pos.clone();
                     return;
                 }
                 self.add_unit(db, unit_info, InfoLevel::Partial);
@@ -0,0 +1,15 @@
+// See LICENSE file for copyright and license details.
+
+extern crate cgmath;
+extern crate common;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+pub mod core;
+pub mod command;
+pub mod db;
+pub mod map;
+pub mod unit;
+pub mod internal_state;
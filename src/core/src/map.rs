@@ -0,0 +1,44 @@
+// See LICENSE file for copyright and license details.
+
+use common::types::{MapPos, Size2};
+
+/// A terrain kind a tile can have.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+pub enum Terrain {
+    Plain,
+    Trees,
+}
+
+/// A rectangular grid of `T`, one per tile.
+pub struct Map<T> {
+    size: Size2,
+    tiles: Vec<T>,
+}
+
+impl<T: Clone> Map<T> {
+    pub fn new(size: &Size2, default: T) -> Map<T> {
+        let tile_count = (size.w * size.h) as usize;
+        Map {
+            size: size.clone(),
+            tiles: vec![default; tile_count],
+        }
+    }
+
+    fn index(&self, pos: &MapPos) -> usize {
+        (pos.v.y * self.size.w + pos.v.x) as usize
+    }
+
+    pub fn is_inboard(&self, pos: &MapPos) -> bool {
+        pos.v.x >= 0 && pos.v.x < self.size.w
+            && pos.v.y >= 0 && pos.v.y < self.size.h
+    }
+
+    pub fn tile(&self, pos: &MapPos) -> &T {
+        &self.tiles[self.index(pos)]
+    }
+
+    pub fn tile_mut(&mut self, pos: &MapPos) -> &mut T {
+        let i = self.index(pos);
+        &mut self.tiles[i]
+    }
+}
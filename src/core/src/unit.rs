@@ -0,0 +1,20 @@
+// See LICENSE file for copyright and license details.
+
+use common::types::{PlayerId, UnitId, MapPos};
+use db::UnitTypeId;
+
+/// A unit instance as known by whoever holds this `InternalState`: fully for
+/// the owner's own units, partially (see `InternalState::add_unit`) for
+/// spotted enemies.
+pub struct Unit {
+    pub id: UnitId,
+    pub pos: MapPos,
+    pub player_id: PlayerId,
+    pub type_id: UnitTypeId,
+    pub move_points: i32,
+    pub attack_points: i32,
+    pub reactive_attack_points: Option<i32>,
+    pub count: i32,
+    pub morale: i32,
+    pub passanger_id: Option<UnitId>,
+}